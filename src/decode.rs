@@ -0,0 +1,226 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use dasp::Sample;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, SeekMode, SeekTo};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use symphonia::core::units::Time;
+
+/// Errors that can surface while decoding an input file, so a bad or
+/// unsupported file can be reported back to the caller instead of aborting
+/// the process via `.expect()`/`panic!`.
+#[derive(Debug)]
+pub enum DecodeError {
+    Io(std::io::Error),
+    UnsupportedFormat(SymphoniaError),
+    NoSupportedTrack,
+    UnsupportedCodec(SymphoniaError),
+    StreamReset,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Io(err) => write!(f, "failed to open media: {err}"),
+            DecodeError::UnsupportedFormat(err) => write!(f, "unsupported format: {err}"),
+            DecodeError::NoSupportedTrack => write!(f, "no supported audio tracks"),
+            DecodeError::UnsupportedCodec(err) => write!(f, "unsupported codec: {err}"),
+            DecodeError::StreamReset => write!(
+                f,
+                "track list changed mid-stream (e.g. chained OGG physical streams), which isn't supported"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl From<std::io::Error> for DecodeError {
+    fn from(err: std::io::Error) -> Self {
+        DecodeError::Io(err)
+    }
+}
+
+// Builds a probe hint from the input file's actual extension instead of always
+// hinting `.mp3`, so Symphonia's probe picks the right format reader for any
+// supported container. Files with no extension get an empty (no-op) hint.
+fn hint_for(filepath: &PathBuf) -> Hint {
+    let mut hint = Hint::new();
+    if let Some(ext) = filepath.extension().and_then(|ext| ext.to_str()) {
+        hint.with_extension(ext);
+    }
+    hint
+}
+
+/// Interleaved samples decoded from a file, plus the channel count and sample
+/// rate Symphonia's codec params reported for it — so callers can build a
+/// correct `WavSpec` for any input container instead of assuming mono/a fixed
+/// sample rate.
+pub struct DecodedAudio {
+    pub samples: Vec<f64>,
+    pub channels: u16,
+    pub sample_rate: u32,
+}
+
+/// Decodes `filepath` into interleaved `f64` samples. If `start_secs` is given, the
+/// format reader is seeked there first (seeks snap to packet boundaries, so the
+/// landed position is reported); if `duration_secs` is given, decoding stops once
+/// that much audio (measured from the landed start) has been produced.
+///
+/// This still materializes the whole (requested range of the) file into memory:
+/// `Track::resample`/`time_stretch`/`normalize` need the full signal for their
+/// `Sinc`/phase-vocoder/RMS passes (as does `source::resampled`, for the same
+/// per-channel-Sinc reason). For live playback with no *output file*, use
+/// `source::SymphoniaSource`/`source::resampled` instead, which never go through
+/// this function or `self.target_fp`.
+pub fn get_samples_from_fp(
+    filepath: &PathBuf,
+    start_secs: Option<f64>,
+    duration_secs: Option<f64>,
+) -> Result<DecodedAudio, DecodeError> {
+    // Open the media source.
+    let src = std::fs::File::open(filepath)?;
+
+    // Create the media source stream.
+    let mss = MediaSourceStream::new(Box::new(src), Default::default());
+
+    // Create a probe hint from the file's actual extension.
+    let hint = hint_for(filepath);
+
+    // Use the default options for metadata and format readers.
+    let meta_opts: MetadataOptions = Default::default();
+    let fmt_opts: FormatOptions = Default::default();
+
+    // Probe the media source.
+    let probed = symphonia::default::get_probe()
+        .format(&hint, mss, &fmt_opts, &meta_opts)
+        .map_err(DecodeError::UnsupportedFormat)?;
+
+    // Get the instantiated format reader.
+    let mut format = probed.format;
+
+    // Find the first audio track with a known (decodeable) codec.
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or(DecodeError::NoSupportedTrack)?;
+
+    // Use the default options for the decoder.
+    let dec_opts: DecoderOptions = Default::default();
+
+    // Create a decoder for the track.
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &dec_opts)
+        .map_err(DecodeError::UnsupportedCodec)?;
+
+    // Store the track identifier, it will be used to filter packets.
+    let track_id = track.id;
+    let sample_rate_hz = track.codec_params.sample_rate.unwrap_or(44_100);
+    let num_channels = track
+        .codec_params
+        .channels
+        .map(|c| c.count())
+        .unwrap_or(1);
+    let sample_rate = sample_rate_hz as f64;
+    let channels = num_channels as f64;
+
+    if let Some(secs) = start_secs.filter(|secs| *secs > 0.0) {
+        let time = Time { seconds: secs.trunc() as u64, frac: secs.fract() };
+        let seeked_to = format
+            .seek(SeekMode::Accurate, SeekTo::Time { time, track_id: Some(track_id) })
+            .map_err(DecodeError::UnsupportedFormat)?;
+        let landed = seeked_to.actual_ts as f64 / sample_rate;
+        println!("Requested start {secs}s, landed at {landed}s (seeks snap to packet boundaries)");
+    }
+    let max_samples = duration_secs.map(|secs| (secs * sample_rate * channels).round() as usize);
+
+    let mut samples = vec![];
+
+    // The decode loop.
+    loop {
+        if max_samples.is_some_and(|max| samples.len() >= max) {
+            break;
+        }
+        // Get the next packet from the media format.
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::ResetRequired) => {
+                // The track list has changed (e.g. chained OGG physical streams) and would
+                // need to be re-examined with a new set of decoders to continue. That's an
+                // advanced feature we don't support yet, so surface it as a recoverable error
+                // instead of panicking.
+                return Err(DecodeError::StreamReset);
+            }
+            Err(SymphoniaError::IoError(_)) => {
+                println!("Reached end of stream");
+                break;
+            }
+            Err(err) => {
+                // An unrecoverable error occurred, halt decoding.
+                return Err(DecodeError::UnsupportedFormat(err));
+            }
+        };
+
+        // Consume any new metadata that has been read since the last packet.
+        while !format.metadata().is_latest() {
+            // Pop the old head of the metadata queue.
+            format.metadata().pop();
+
+            // Consume the new metadata at the head of the metadata queue.
+        }
+
+        // If the packet does not belong to the selected track, skip over it.
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let mut samples_buf = None;
+        // Decode the packet into audio samples.
+        match decoder.decode(&packet) {
+            Ok(decoded) => {
+                // Consume the decoded audio samples (see below).
+                if samples_buf.is_none() {
+                    // Get the audio buffer specification.
+                    let spec = *decoded.spec();
+
+                    // Get the capacity of the decoded buffer. Note: This is capacity, not length!
+                    let duration = decoded.capacity() as u64;
+
+                    // Create the f64 sample buffer.
+                    samples_buf = Some(SampleBuffer::<f64>::new(duration, spec));
+                }
+
+                // Copy the decoded audio buffer into the sample buffer in an interleaved format.
+                if let Some(buf) = &mut samples_buf {
+                    buf.copy_interleaved_ref(decoded);
+                    for sample in buf.samples() {
+                        samples.push(sample.to_sample::<f64>());
+                    }
+                }
+            }
+            Err(SymphoniaError::IoError(_)) => {
+                // The packet failed to decode due to an IO error, skip the packet.
+                continue;
+            }
+            Err(SymphoniaError::DecodeError(_)) => {
+                // The packet failed to decode due to invalid data, skip the packet.
+                continue;
+            }
+            Err(err) => {
+                // An unrecoverable error occurred, halt decoding.
+                return Err(DecodeError::UnsupportedFormat(err));
+            }
+        }
+    }
+    Ok(DecodedAudio {
+        samples,
+        channels: num_channels as u16,
+        sample_rate: sample_rate_hz,
+    })
+}
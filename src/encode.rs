@@ -0,0 +1,240 @@
+use std::fmt;
+use std::path::Path;
+
+use dasp::Sample;
+use hound::{WavSpec, WavWriter};
+
+/// Errors that can surface while encoding output, so a format/encoder
+/// limitation (e.g. MP3's 2-channel ceiling) can be reported back to the
+/// caller instead of aborting the process via `.expect()`/`panic!` (see
+/// `decode::DecodeError`, which takes the same approach on the input side).
+#[derive(Debug)]
+pub enum EncodeError {
+    UnsupportedChannelCount { format: &'static str, channels: u16 },
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncodeError::UnsupportedChannelCount { format, channels } => {
+                write!(f, "{format} does not support {channels}-channel audio")
+            }
+        }
+    }
+}
+
+impl std::error::Error for EncodeError {}
+
+/// Encodes interleaved `f64` frames to a file, dispatching on the target
+/// path's extension. `.flac`, `.ogg` and `.mp3` hand off to a lossless (FLAC)
+/// or lossy (Ogg/Vorbis, MP3) encoder so `--output-file` can point at any of
+/// them; `.wav`, and any other or missing extension, use the `hound` WAV path.
+pub enum Encoder {
+    Wav,
+    Flac,
+    Ogg,
+    Mp3,
+}
+
+impl Encoder {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        match path
+            .as_ref()
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase())
+            .as_deref()
+        {
+            Some("flac") => Encoder::Flac,
+            Some("ogg") => Encoder::Ogg,
+            Some("mp3") => Encoder::Mp3,
+            _ => Encoder::Wav,
+        }
+    }
+
+    /// Writes per-channel sample buffers (one `Vec` per channel, interleaved frame by
+    /// frame here wherever the encoder wants interleaved input) out in the chosen
+    /// format, preserving `spec`'s channel count and bit depth where the format allows it.
+    /// MP3 is the only format whose channel limit is checked here and returned as an
+    /// `EncodeError`, since LAME only supports mono/stereo; the other encoders are
+    /// handed `spec`'s channel count as is.
+    pub fn write(&self, channels: &[Vec<f64>], target_fp: &Path, spec: WavSpec) -> Result<(), EncodeError> {
+        match self {
+            Encoder::Wav => {
+                write_wav(channels, target_fp, spec);
+                Ok(())
+            }
+            Encoder::Flac => {
+                write_flac(channels, target_fp, spec);
+                Ok(())
+            }
+            Encoder::Ogg => {
+                write_ogg(channels, target_fp, spec);
+                Ok(())
+            }
+            Encoder::Mp3 => write_mp3(channels, target_fp, spec),
+        }
+    }
+}
+
+fn write_wav(channels: &[Vec<f64>], target_fp: &Path, spec: WavSpec) {
+    let mut writer = WavWriter::create(target_fp, spec).unwrap();
+    let frame_count = channels.iter().map(|c| c.len()).min().unwrap_or(0);
+    for frame_idx in 0..frame_count {
+        for channel in channels {
+            let sample = channel[frame_idx];
+            match spec.sample_format {
+                hound::SampleFormat::Float => writer.write_sample(sample as f32).unwrap(),
+                hound::SampleFormat::Int => writer
+                    .write_sample(to_depth_i32(sample, spec.bits_per_sample))
+                    .unwrap(),
+            }
+        }
+    }
+}
+
+// Scales a full-range `f64` sample (`[-1.0, 1.0]`) into the integer range implied by
+// `bits_per_sample`, widened to `i32` as `process_interleaved` expects. `dasp`'s
+// `to_sample` conversions are full-range for whatever integer width they target, so
+// this has to dispatch on the *declared* bit depth rather than always going via
+// `i32`, or encoders declared at a narrower depth (e.g. 16-bit WAV-sourced tracks)
+// end up fed samples scaled for a much wider range.
+fn to_depth_i32(sample: f64, bits_per_sample: u16) -> i32 {
+    match bits_per_sample {
+        8 => sample.to_sample::<i8>() as i32,
+        16 => sample.to_sample::<i16>() as i32,
+        24 => (sample.clamp(-1.0, 1.0) * 8_388_607.0).round() as i32,
+        _ => sample.to_sample::<i32>(),
+    }
+}
+
+fn write_flac(channels: &[Vec<f64>], target_fp: &Path, spec: WavSpec) {
+    // `flac-bound`/`libflac-sys` provide a `FlacEncoder` builder that mirrors
+    // `hound::WavWriter`'s create-then-write-sample shape; channel count and bit
+    // depth carry over unchanged since FLAC is lossless.
+    let mut encoder = flac_bound::FlacEncoder::new()
+        .expect("failed to allocate FLAC encoder")
+        .channels(spec.channels as u32)
+        .bits_per_sample(spec.bits_per_sample as u32)
+        .sample_rate(spec.sample_rate)
+        .init_file(target_fp)
+        .expect("failed to open FLAC output file");
+
+    let frame_count = channels.iter().map(|c| c.len()).min().unwrap_or(0);
+    let mut interleaved = Vec::with_capacity(frame_count * channels.len());
+    for frame_idx in 0..frame_count {
+        for channel in channels {
+            interleaved.push(to_depth_i32(channel[frame_idx], spec.bits_per_sample));
+        }
+    }
+    encoder
+        .process_interleaved(&interleaved, frame_count as u32)
+        .expect("failed to encode FLAC samples");
+    encoder.finish().expect("failed to finalize FLAC stream");
+}
+
+fn write_ogg(channels: &[Vec<f64>], target_fp: &Path, spec: WavSpec) {
+    // `vorbis_rs` encodes planar `f32` audio: one buffer per channel, not interleaved.
+    let file = std::fs::File::create(target_fp).expect("failed to create Ogg output file");
+    let mut encoder =
+        vorbis_rs::VorbisEncoderBuilder::new(spec.sample_rate, spec.channels as u8, file)
+            .expect("failed to configure Vorbis encoder")
+            .build()
+            .expect("failed to build Vorbis encoder");
+
+    let frame_count = channels.iter().map(|c| c.len()).min().unwrap_or(0);
+    let per_channel: Vec<Vec<f32>> = channels
+        .iter()
+        .map(|c| c[..frame_count].iter().map(|s| s.to_sample::<f32>()).collect())
+        .collect();
+    encoder.encode_audio_block(&per_channel).expect("failed to encode Vorbis samples");
+    encoder.finish().expect("failed to finalize Ogg stream");
+}
+
+fn write_mp3(channels: &[Vec<f64>], target_fp: &Path, spec: WavSpec) -> Result<(), EncodeError> {
+    // `mp3lame-encoder` wraps libmp3lame; samples are fed to it as one interleaved
+    // i16 buffer (`InterleavedPcm`). LAME itself only
+    // supports mono/stereo, so anything wider (e.g. a multichannel source passed
+    // straight through from `Track::resample`) needs to be rejected here rather
+    // than left to panic inside `set_num_channels`.
+    use mp3lame_encoder::{Builder, FlushNoGap, InterleavedPcm};
+
+    if spec.channels == 0 || spec.channels > 2 {
+        return Err(EncodeError::UnsupportedChannelCount { format: "MP3", channels: spec.channels });
+    }
+
+    let mut builder = Builder::new().expect("failed to allocate LAME encoder");
+    builder.set_num_channels(spec.channels as u8).expect("unsupported channel count");
+    builder.set_sample_rate(spec.sample_rate).expect("unsupported sample rate");
+    let mut lame = builder.build().expect("failed to configure LAME encoder");
+
+    let frame_count = channels.iter().map(|c| c.len()).min().unwrap_or(0);
+    let mut interleaved = Vec::with_capacity(frame_count * channels.len());
+    for frame_idx in 0..frame_count {
+        for channel in channels {
+            interleaved.push(channel[frame_idx].to_sample::<i16>());
+        }
+    }
+
+    let mut mp3_out = Vec::new();
+    mp3_out.reserve(mp3lame_encoder::max_required_buffer_size(frame_count));
+    lame.encode(InterleavedPcm(&interleaved), &mut mp3_out).expect("failed to encode MP3 samples");
+    lame.flush::<FlushNoGap>(&mut mp3_out).expect("failed to finalize MP3 stream");
+
+    std::fs::write(target_fp, mp3_out).expect("failed to write MP3 output file");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flac_round_trip_preserves_16_bit_amplitude() {
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: 44_100,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let samples = vec![vec![0.5, -0.5, 0.25, -1.0, 0.0]];
+
+        let target_fp = std::env::temp_dir().join("nightcore_flac_round_trip_test.flac");
+        write_flac(&samples, &target_fp, spec);
+
+        let mut reader = claxon::FlacReader::open(&target_fp).expect("failed to open FLAC output");
+        let decoded: Vec<i32> = reader
+            .samples()
+            .map(|s| s.expect("failed to decode FLAC sample"))
+            .collect();
+        std::fs::remove_file(&target_fp).ok();
+
+        // 16-bit full range: 0.5 -> ~16384, not ~1.07e9 (the 32-bit-range bug this
+        // guards against).
+        assert_eq!(decoded.len(), samples[0].len());
+        for (decoded_sample, original) in decoded.iter().zip(&samples[0]) {
+            let expected = (original * i16::MAX as f64).round() as i32;
+            assert!((decoded_sample - expected).abs() <= 1);
+        }
+    }
+
+    #[test]
+    fn write_mp3_rejects_unsupported_channel_counts() {
+        let spec = WavSpec {
+            channels: 6,
+            sample_rate: 44_100,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let channels = vec![vec![0.0; 4]; 6];
+        let target_fp = std::env::temp_dir().join("nightcore_mp3_channel_count_test.mp3");
+
+        let result = write_mp3(&channels, &target_fp, spec);
+
+        assert!(matches!(
+            result,
+            Err(EncodeError::UnsupportedChannelCount { format: "MP3", channels: 6 })
+        ));
+        assert!(!target_fp.exists());
+    }
+}
@@ -8,23 +8,28 @@ use rodio::{
     Decoder, OutputStream, source::Source
 };
 use dasp::{
-    interpolate::sinc::Sinc, 
-    ring_buffer, 
+    interpolate::sinc::Sinc,
+    ring_buffer,
     signal,
-    Sample, 
+    Sample,
     Signal
 };
-use hound::{SampleFormat, WavSpec, WavReader, WavWriter};
+use hound::{SampleFormat, WavSpec, WavReader};
 use clap::Parser;
-use symphonia::core::{
-    audio::SampleBuffer,
-    codecs::{DecoderOptions, CODEC_TYPE_NULL},
-    errors::Error,
-    formats::FormatOptions,
-    io::MediaSourceStream,
-    meta::MetadataOptions,
-    probe::Hint
-};
+
+mod source;
+pub use source::{SymphoniaSource, ResampledSource};
+
+mod vocoder;
+
+mod encode;
+use encode::Encoder;
+pub use encode::EncodeError;
+
+mod normalize;
+
+mod decode;
+pub use decode::DecodeError;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -33,7 +38,22 @@ struct Args {
     input_file: String,
 
     #[arg(short, long, default_value = "output.wav")]
-    output_file: String
+    output_file: String,
+
+    /// Loudness-normalize the input to this target in dBFS before resampling
+    /// (e.g. -14.0 approximates a -14 LUFS ReplayGain-style target).
+    #[arg(short, long)]
+    normalize: Option<f64>,
+
+    /// Seek to this position (in seconds) before decoding, so only the selected
+    /// segment of a long file is decoded and resampled.
+    #[arg(long)]
+    start: Option<f64>,
+
+    /// Stop decoding this many seconds after `--start`, instead of decoding to
+    /// the end of the file.
+    #[arg(long)]
+    duration: Option<f64>
 }
 
 impl Args {
@@ -54,45 +74,140 @@ pub struct Track {
 }
 
 impl Track {
-    fn from(orig_fp: PathBuf, target_fp: PathBuf, spec: WavSpec) -> Self {
-        let samples = get_samples_from_fp(&orig_fp);
-        Self {
+    fn from(
+        orig_fp: PathBuf,
+        target_fp: PathBuf,
+        spec: WavSpec,
+        start_secs: Option<f64>,
+        duration_secs: Option<f64>,
+    ) -> Result<Self, DecodeError> {
+        let decoded = decode::get_samples_from_fp(&orig_fp, start_secs, duration_secs)?;
+        // The channel count/sample rate the caller guessed at (e.g. the mono/64kHz
+        // fallback for non-WAV input) are placeholders; the real values come from
+        // Symphonia's codec params once the file's actually been decoded, so use those.
+        let mut spec = spec;
+        spec.channels = decoded.channels;
+        spec.sample_rate = decoded.sample_rate;
+        Ok(Self {
             orig_fp,
             target_fp,
             spec,
-            samples
-        }
+            samples: decoded.samples
+        })
     }
 
     pub fn resample(&self, f_ratio: f32) { // f_ratio is the ratio to change the sample rate by
         dbg!(f_ratio);
-        let signal = signal::from_interleaved_samples_iter(self.samples.clone());
-    
-        // Convert the signal's sample rate using `Sinc` interpolation.
-        let ring_buffer = ring_buffer::Fixed::from([[0.0]; 100]);
-        let sinc = Sinc::new(ring_buffer);
+        let channels = self.spec.channels as usize;
+        let deinterleaved = deinterleave(&self.samples, channels);
         let start = Instant::now();
-        let new_signal = signal.scale_hz(sinc, f_ratio as f64);
+
+        // Resample each channel independently so stereo/multichannel input keeps its
+        // channel layout instead of collapsing to the left channel.
+        let resampled: Vec<Vec<f64>> = deinterleaved
+            .into_iter()
+            .map(|channel_samples| {
+                let signal = signal::from_interleaved_samples_iter(channel_samples.into_iter());
+                let ring_buffer = ring_buffer::Fixed::from([[0.0]; 100]);
+                let sinc = Sinc::new(ring_buffer);
+                signal
+                    .scale_hz(sinc, f_ratio as f64)
+                    .until_exhausted()
+                    .map(|frame| frame[0])
+                    .collect()
+            })
+            .collect();
+
         let mut target_spec = self.spec;
         target_spec.sample_rate = (self.spec.sample_rate as f32 * f_ratio) as u32;
-    
-        // Write the result to a new file.
-        let mut writer = WavWriter::create(&self.target_fp, target_spec).unwrap();
-        for frame in new_signal.until_exhausted() {
-            writer.write_sample(frame[0].to_sample::<i16>()).unwrap();
-        }
-    
+
+        write_channels(&resampled, &self.target_fp, target_spec);
+
         let duration = start.elapsed();
         println!("Took {:?} to resample", duration);
     }
 
+    // Changes tempo by `ratio` without affecting pitch, via a phase-vocoder
+    // time-stretch. `ratio > 1.0` speeds the track up, `ratio < 1.0` slows it down
+    // (the vocoder's length factor is `1 / ratio`).
+    pub fn time_stretch(&self, ratio: f64) {
+        dbg!(ratio);
+        let channels = self.spec.channels as usize;
+        let deinterleaved = deinterleave(&self.samples, channels);
+        let start = Instant::now();
+
+        let stretched: Vec<Vec<f64>> = deinterleaved
+            .iter()
+            .map(|channel_samples| vocoder::time_stretch(channel_samples, 1.0 / ratio))
+            .collect();
+
+        write_channels(&stretched, &self.target_fp, self.spec);
+
+        let duration = start.elapsed();
+        println!("Took {:?} to time-stretch", duration);
+    }
+
+    // Changes pitch by `ratio` without affecting tempo: time-stretch by `1/ratio`
+    // (in `time_stretch`'s tempo terms, so the track becomes `ratio` times longer),
+    // then resample by `ratio` to restore the original duration at the new pitch.
+    pub fn pitch_shift(&self, ratio: f64) {
+        dbg!(ratio);
+        let channels = self.spec.channels as usize;
+        let deinterleaved = deinterleave(&self.samples, channels);
+
+        let stretched: Vec<Vec<f64>> = deinterleaved
+            .iter()
+            .map(|channel_samples| vocoder::time_stretch(channel_samples, ratio))
+            .collect();
+
+        let resampled: Vec<Vec<f64>> = stretched
+            .into_iter()
+            .map(|channel_samples| {
+                let signal = signal::from_interleaved_samples_iter(channel_samples.into_iter());
+                let ring_buffer = ring_buffer::Fixed::from([[0.0]; 100]);
+                let sinc = Sinc::new(ring_buffer);
+                signal
+                    .scale_hz(sinc, ratio)
+                    .until_exhausted()
+                    .map(|frame| frame[0])
+                    .collect()
+            })
+            .collect();
+
+        write_channels(&resampled, &self.target_fp, self.spec);
+    }
+
+    // Loudness-normalizes the decoded samples toward `target_db` in place, with
+    // peak limiting so the applied gain never clips. Call before resampling/
+    // time-stretching so those stages see already-normalized samples.
+    pub fn normalize(&mut self, target_db: f64) {
+        dbg!(target_db);
+        normalize::normalize(&mut self.samples, target_db);
+    }
+
     pub fn playback(&self, playtime: u64) {
         println!("Playing original track...");
         playback(&self.orig_fp, playtime).unwrap();
-    
+
         println!("Playing resampled track...");
         playback(&self.target_fp, playtime).unwrap();
     }
+
+    // Plays the track resampled by `f_ratio` live: decodes through a fresh
+    // `SymphoniaSource` and runs the same per-channel Sinc resample
+    // `Track::resample` applies (see `source::resampled`), instead of writing a
+    // resampled `self.target_fp` and reopening it — so a nightcored track can be
+    // heard with no intermediate output file.
+    pub fn play_streamed(&self, f_ratio: f32, playtime: u64) {
+        dbg!(f_ratio);
+        let (_stream, stream_handle) = OutputStream::try_default().unwrap();
+        let source = SymphoniaSource::new(&self.orig_fp).unwrap();
+        let resampled: ResampledSource<_> = source::resampled(source, f_ratio as f64);
+        stream_handle
+            .play_raw(resampled.convert_samples())
+            .unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(playtime));
+    }
 }
 
 impl Default for Track {
@@ -111,12 +226,40 @@ impl Default for Track {
             }
         };
         dbg!(spec);
-        Track::from( 
+        let mut track = Track::from(
             args.get_input_fp(),
             args.get_output_fp(),
-            spec
-        )
+            spec,
+            args.start,
+            args.duration
+        ).expect("failed to decode input file");
+        if let Some(target_db) = args.normalize {
+            track.normalize(target_db);
+        }
+        track
+    }
+}
+
+// Splits interleaved samples (as produced by `get_samples_from_fp`) into one
+// `Vec<f64>` per channel so each channel can be processed independently.
+pub(crate) fn deinterleave(samples: &[f64], channels: usize) -> Vec<Vec<f64>> {
+    let channels = channels.max(1);
+    let mut out = vec![Vec::with_capacity(samples.len() / channels); channels];
+    for (i, sample) in samples.iter().enumerate() {
+        out[i % channels].push(*sample);
     }
+    out
+}
+
+// Re-interleaves per-channel sample buffers and writes them out in whichever
+// format `target_fp`'s extension selects (see `encode::Encoder`). Panics with
+// the encoder's own message (e.g. MP3's mono/stereo ceiling) rather than
+// silently dropping the output, since none of `Track`'s methods have anywhere
+// else to route a write failure.
+fn write_channels(channels: &[Vec<f64>], target_fp: &PathBuf, spec: WavSpec) {
+    Encoder::from_path(target_fp)
+        .write(channels, target_fp, spec)
+        .expect("failed to encode output");
 }
 
 fn load_file(filepath: &PathBuf) -> Option<File> {
@@ -144,124 +287,6 @@ fn playback(filepath: &PathBuf, playtime: u64) -> Result<String, rodio::PlayErro
     }
 }
 
-fn get_samples_from_fp(filepath: &PathBuf) -> Vec::<f64> {
-    // Open the media source.
-    let src = std::fs::File::open(filepath).expect("failed to open media");
-
-    // Create the media source stream.
-    let mss = MediaSourceStream::new(Box::new(src), Default::default());
-
-    // Create a probe hint using the file's extension. [Optional]
-    let mut hint = Hint::new();
-    hint.with_extension("mp3");
-
-    // Use the default options for metadata and format readers.
-    let meta_opts: MetadataOptions = Default::default();
-    let fmt_opts: FormatOptions = Default::default();
-
-    // Probe the media source.
-    let probed = symphonia::default::get_probe()
-        .format(&hint, mss, &fmt_opts, &meta_opts)
-        .expect("unsupported format");
-
-    // Get the instantiated format reader.
-    let mut format = probed.format;
-
-    // Find the first audio track with a known (decodeable) codec.
-    let track = format
-        .tracks()
-        .iter()
-        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
-        .expect("no supported audio tracks");
-
-    // Use the default options for the decoder.
-    let dec_opts: DecoderOptions = Default::default();
-
-    // Create a decoder for the track.
-    let mut decoder = symphonia::default::get_codecs()
-        .make(&track.codec_params, &dec_opts)
-        .expect("unsupported codec");
-
-    // Store the track identifier, it will be used to filter packets.
-    let track_id = track.id;
-    let mut samples = vec![];
-
-    // The decode loop.
-    loop {
-        // Get the next packet from the media format.
-        let packet = match format.next_packet() {
-            Ok(packet) => packet,
-            Err(Error::ResetRequired) => {
-                // The track list has been changed. Re-examine it and create a new set of decoders,
-                // then restart the decode loop. This is an advanced feature and it is not
-                // unreasonable to consider this "the end." As of v0.5.0, the only usage of this is
-                // for chained OGG physical streams.
-                unimplemented!();
-            }
-            Err(Error::IoError(_)) => {
-                println!("Reached end of stream");
-                break
-            }
-            Err(err) => {
-                // A unrecoverable error occured, halt decoding.
-                panic!("{}", err);
-            }
-        };
-
-        // Consume any new metadata that has been read since the last packet.
-        while !format.metadata().is_latest() {
-            // Pop the old head of the metadata queue.
-            format.metadata().pop();
-
-            // Consume the new metadata at the head of the metadata queue.
-        }
-
-        // If the packet does not belong to the selected track, skip over it.
-        if packet.track_id() != track_id {
-            continue;
-        }
-
-        let mut samples_buf = None;
-        // Decode the packet into audio samples.
-        match decoder.decode(&packet) {
-            Ok(decoded) => {
-                // Consume the decoded audio samples (see below).
-                if samples_buf.is_none() {
-                    // Get the audio buffer specification.
-                    let spec = *decoded.spec();
-
-                    // Get the capacity of the decoded buffer. Note: This is capacity, not length!
-                    let duration = decoded.capacity() as u64;
-
-                    // Create the f64 sample buffer.
-                    samples_buf = Some(SampleBuffer::<f64>::new(duration, spec));
-                }
-
-                // Copy the decoded audio buffer into the sample buffer in an interleaved format.
-                if let Some(buf) = &mut samples_buf {
-                    buf.copy_interleaved_ref(decoded);
-                    for sample in buf.samples() {
-                        samples.push(sample.to_sample::<f64>());
-                    }
-                }
-            }
-            Err(Error::IoError(_)) => {
-                // The packet failed to decode due to an IO error, skip the packet.
-                continue;
-            }
-            Err(Error::DecodeError(_)) => {
-                // The packet failed to decode due to invalid data, skip the packet.
-                continue;
-            }
-            Err(err) => {
-                // An unrecoverable error occured, halt decoding.
-                panic!("{}", err);
-            }
-        }
-    }
-    samples
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,65 @@
+/// Computes an RMS-based gain factor that brings `samples` toward `target_db`
+/// (dBFS, e.g. `-14.0` to approximate a -14 LUFS ReplayGain-style target), then
+/// scales every sample by it, clamping the factor so the loudest sample never
+/// clips past full scale (`[-1.0, 1.0]`).
+pub fn normalize(samples: &mut [f64], target_db: f64) {
+    if samples.is_empty() {
+        return;
+    }
+
+    let mean_square: f64 = samples.iter().map(|s| s * s).sum::<f64>() / samples.len() as f64;
+    let rms = mean_square.sqrt();
+    if rms == 0.0 {
+        return;
+    }
+
+    let target_rms = 10f64.powf(target_db / 20.0);
+    let mut gain = target_rms / rms;
+
+    // Peak-limit: never let the applied gain push the loudest sample past +/-1.0.
+    let peak = samples.iter().fold(0.0_f64, |max, s| max.max(s.abs()));
+    if peak > 0.0 {
+        gain = gain.min(1.0 / peak);
+    }
+
+    for sample in samples.iter_mut() {
+        *sample *= gain;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rms_converges_toward_target() {
+        let mut samples = vec![0.1, -0.1, 0.1, -0.1];
+        normalize(&mut samples, -14.0);
+        let rms = (samples.iter().map(|s| s * s).sum::<f64>() / samples.len() as f64).sqrt();
+        let target_rms = 10f64.powf(-14.0 / 20.0);
+        assert!((rms - target_rms).abs() < 1e-9);
+    }
+
+    #[test]
+    fn peak_limiter_prevents_clipping() {
+        let mut samples = vec![0.9, -1.0, 0.95, -0.2];
+        normalize(&mut samples, 0.0);
+        for sample in samples {
+            assert!(sample.abs() <= 1.0);
+        }
+    }
+
+    #[test]
+    fn empty_slice_is_noop() {
+        let mut samples: Vec<f64> = vec![];
+        normalize(&mut samples, -14.0);
+        assert!(samples.is_empty());
+    }
+
+    #[test]
+    fn silent_input_is_noop() {
+        let mut samples = vec![0.0, 0.0, 0.0];
+        normalize(&mut samples, -14.0);
+        assert_eq!(samples, vec![0.0, 0.0, 0.0]);
+    }
+}
@@ -0,0 +1,212 @@
+use std::fs::File;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use dasp::{interpolate::sinc::Sinc, ring_buffer, signal, Sample as DaspSample, Signal};
+use rodio::Source;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{Decoder, DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error;
+use symphonia::core::formats::{FormatOptions, FormatReader};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+/// A `rodio::Source` that decodes a file one Symphonia packet at a time,
+/// instead of eagerly decoding the whole file into memory like
+/// `get_samples_from_fp` does. Samples are handed out lazily from an
+/// internal per-packet buffer that gets refilled as it's drained.
+pub struct SymphoniaSource {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    channels: u16,
+    sample_rate: u32,
+    buf: Vec<i16>,
+    buf_pos: usize,
+}
+
+impl SymphoniaSource {
+    pub fn new(filepath: &PathBuf) -> Result<Self, Error> {
+        let src = File::open(filepath)?;
+        let mss = MediaSourceStream::new(Box::new(src), Default::default());
+
+        let mut hint = Hint::new();
+        if let Some(ext) = filepath.extension().and_then(|e| e.to_str()) {
+            hint.with_extension(ext);
+        }
+
+        let meta_opts: MetadataOptions = Default::default();
+        let fmt_opts: FormatOptions = Default::default();
+        let probed = symphonia::default::get_probe().format(&hint, mss, &fmt_opts, &meta_opts)?;
+
+        let format = probed.format;
+        let track = format
+            .tracks()
+            .iter()
+            .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or(Error::Unsupported("no supported audio tracks"))?;
+
+        let channels = track
+            .codec_params
+            .channels
+            .map(|c| c.count() as u16)
+            .unwrap_or(1);
+        let sample_rate = track.codec_params.sample_rate.unwrap_or(44_100);
+        let track_id = track.id;
+
+        let dec_opts: DecoderOptions = Default::default();
+        let decoder = symphonia::default::get_codecs().make(&track.codec_params, &dec_opts)?;
+
+        Ok(Self {
+            format,
+            decoder,
+            track_id,
+            channels,
+            sample_rate,
+            buf: Vec::new(),
+            buf_pos: 0,
+        })
+    }
+
+    // Decodes packets until one belonging to our track yields samples, filling `self.buf`.
+    // Returns `false` once the stream is exhausted.
+    fn fill_buf(&mut self) -> bool {
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                Err(Error::IoError(_)) => return false,
+                Err(Error::ResetRequired) => return false,
+                Err(_) => return false,
+            };
+
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+
+            match self.decoder.decode(&packet) {
+                Ok(decoded) => {
+                    let spec = *decoded.spec();
+                    let duration = decoded.capacity() as u64;
+                    let mut sample_buf = SampleBuffer::<i16>::new(duration, spec);
+                    sample_buf.copy_interleaved_ref(decoded);
+                    self.buf = sample_buf.samples().to_vec();
+                    self.buf_pos = 0;
+                    if !self.buf.is_empty() {
+                        return true;
+                    }
+                }
+                Err(Error::IoError(_)) | Err(Error::DecodeError(_)) => continue,
+                Err(_) => return false,
+            }
+        }
+    }
+}
+
+impl Iterator for SymphoniaSource {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        if self.buf_pos >= self.buf.len() && !self.fill_buf() {
+            return None;
+        }
+        let sample = self.buf[self.buf_pos];
+        self.buf_pos += 1;
+        Some(sample)
+    }
+}
+
+impl Source for SymphoniaSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        Some(self.buf.len() - self.buf_pos)
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// A `rodio::Source` handing out the samples produced by resampling a
+/// `SymphoniaSource` by a fixed ratio, via `source::resampled`.
+pub struct ResampledSource<I: Iterator<Item = i16>> {
+    inner: I,
+    channels: u16,
+    sample_rate: u32,
+}
+
+/// Applies `Track::resample`'s own Sinc-interpolation resample ratio to a
+/// `SymphoniaSource`, channel by channel (same shape as `lib::Track::resample`:
+/// deinterleave, run one `Sinc` pipeline per channel, re-interleave), so a
+/// nightcored track can be heard with no intermediate output file, instead of the
+/// channel-collapsing bug of running every interleaved sample through a single
+/// "mono" Sinc series. This does read `source` to completion up front — per-channel
+/// Sinc interpolation needs each channel's own continuous series, which a flat
+/// interleaved stream doesn't give us one sample at a time.
+pub fn resampled(
+    source: SymphoniaSource,
+    f_ratio: f64,
+) -> ResampledSource<impl Iterator<Item = i16>> {
+    let channels = source.channels();
+    let sample_rate = (source.sample_rate() as f64 * f_ratio) as u32;
+
+    let interleaved: Vec<f64> = source.map(|sample: i16| sample.to_sample::<f64>()).collect();
+    let deinterleaved = crate::deinterleave(&interleaved, channels as usize);
+
+    let resampled_channels: Vec<Vec<i16>> = deinterleaved
+        .into_iter()
+        .map(|channel_samples| {
+            let signal = signal::from_interleaved_samples_iter(channel_samples.into_iter());
+            let ring_buffer = ring_buffer::Fixed::from([[0.0]; 100]);
+            let sinc = Sinc::new(ring_buffer);
+            signal
+                .scale_hz(sinc, f_ratio)
+                .until_exhausted()
+                .map(|frame: [f64; 1]| frame[0].to_sample::<i16>())
+                .collect()
+        })
+        .collect();
+
+    let frame_count = resampled_channels.iter().map(|c| c.len()).min().unwrap_or(0);
+    let mut reinterleaved = Vec::with_capacity(frame_count * resampled_channels.len());
+    for frame_idx in 0..frame_count {
+        for channel in &resampled_channels {
+            reinterleaved.push(channel[frame_idx]);
+        }
+    }
+
+    ResampledSource { inner: reinterleaved.into_iter(), channels, sample_rate }
+}
+
+impl<I: Iterator<Item = i16>> Iterator for ResampledSource<I> {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        self.inner.next()
+    }
+}
+
+impl<I: Iterator<Item = i16>> Source for ResampledSource<I> {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}